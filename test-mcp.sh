@@ -6,6 +6,12 @@ echo "Testing Blazing-ART-MCP Server..."
 echo "================================"
 
 SERVER="./target/release/blazing_art_mcp --entities data/entities.json --events data/events.json"
+INIT='{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}'
+
+# tools/call is rejected before initialize, so send it first and keep the second response
+call() {
+    printf '%s\n' "$INIT" "$1" | $SERVER | sed -n 2p
+}
 
 # Test 1: Initialize
 echo -e "\n1. Testing initialization..."
@@ -17,18 +23,18 @@ echo '{"jsonrpc":"2.0","id":2,"method":"tools/list","params":{}}' | $SERVER | jq
 
 # Test 3: Lookup entity
 echo -e "\n3. Testing entity lookup (Albert Einstein)..."
-echo '{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"lookupEntity","arguments":{"name":"Albert Einstein"}}}' | $SERVER | jq '.result.content[0].text' 2>/dev/null || echo "Raw output shown"
+call '{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"lookupEntity","arguments":{"name":"Albert Einstein"}}}' | jq '.result.content[0].text' 2>/dev/null || echo "Raw output shown"
 
 # Test 4: Find events
 echo -e "\n4. Testing event search (2024-01)..."
-echo '{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"findEvents","arguments":{"prefix":"2024-01"}}}' | $SERVER | head -1
+call '{"jsonrpc":"2.0","id":4,"method":"tools/call","params":{"name":"findEvents","arguments":{"prefix":"2024-01"}}}'
 
 # Test 5: Add new entity
 echo -e "\n5. Testing add entity (Nikola Tesla)..."
-echo '{"jsonrpc":"2.0","id":5,"method":"tools/call","params":{"name":"addEntity","arguments":{"name":"Nikola Tesla","summary":"Serbian-American inventor and electrical engineer","born":"1856","tags":["inventor","electricity","AC-power"]}}}' | $SERVER | head -1
+call '{"jsonrpc":"2.0","id":5,"method":"tools/call","params":{"name":"addEntity","arguments":{"name":"Nikola Tesla","summary":"Serbian-American inventor and electrical engineer","born":"1856","tags":["inventor","electricity","AC-power"]}}}'
 
 # Test 6: Add new event
 echo -e "\n6. Testing add event..."
-echo '{"jsonrpc":"2.0","id":6,"method":"tools/call","params":{"name":"addEvent","arguments":{"description":"Blazing-ART-MCP server successfully deployed","category":"technology"}}}' | $SERVER | head -1
+call '{"jsonrpc":"2.0","id":6,"method":"tools/call","params":{"name":"addEvent","arguments":{"description":"Blazing-ART-MCP server successfully deployed","category":"technology"}}}'
 
 echo -e "\nAll tests completed!"
\ No newline at end of file
@@ -0,0 +1,402 @@
+//! End-to-end tests that run the server binary over STDIO.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread::JoinHandle;
+
+use serde_json::{json, Value};
+
+/// A running server with piped STDIO.
+struct Server {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: Option<BufReader<ChildStdout>>,
+    /// Collects stderr as it is written, so verbose logging never fills the pipe.
+    stderr: JoinHandle<String>,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_blazing_art_mcp"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().map(BufReader::new);
+        let mut pipe = child.stderr.take().unwrap();
+        let stderr = std::thread::spawn(move || {
+            let mut stderr = String::new();
+            pipe.read_to_string(&mut stderr).unwrap();
+            stderr
+        });
+        Self { child, stdin, stdout, stderr }
+    }
+
+    fn send(&mut self, message: &Value) {
+        self.send_raw(format!("{}\n", message).as_bytes());
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) {
+        let stdin = self.stdin.as_mut().unwrap();
+        stdin.write_all(bytes).unwrap();
+        stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut line = String::new();
+        self.stdout.as_mut().unwrap().read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap_or_else(|e| panic!("bad frame {:?}: {}", line, e))
+    }
+
+    /// Reads one LSP-style `Content-Length` framed message.
+    fn receive_framed(&mut self) -> Value {
+        let stdout = self.stdout.as_mut().unwrap();
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            stdout.read_line(&mut header).unwrap();
+            match header.trim_end().split_once(": ") {
+                Some(("Content-Length", value)) => length = Some(value.parse().unwrap()),
+                Some(_) => {}
+                None if header == "\r\n" => break,
+                None => panic!("bad header {:?}", header),
+            }
+        }
+        let mut body = vec![0; length.expect("no Content-Length header")];
+        stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+        self.send(&json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}));
+        self.receive()
+    }
+
+    fn initialize(&mut self) -> Value {
+        self.request(0, "initialize", json!({"protocolVersion": "2024-11-05", "capabilities": {}}))
+    }
+
+    /// Stops reading responses, as a client that has gone away.
+    fn hang_up(&mut self) {
+        self.stdout = None;
+    }
+
+    /// Closes stdin and waits for exit, returning whether it succeeded and its stderr.
+    fn finish(mut self) -> (bool, String) {
+        drop(self.stdin.take());
+        let status = self.child.wait().unwrap();
+        (status.success(), self.stderr.join().unwrap())
+    }
+}
+
+#[test]
+fn client_disconnect_mid_request_is_a_normal_shutdown() {
+    let mut server = Server::start(&[]);
+    server.initialize();
+    // Hang up before the response to this request can be written
+    server.hang_up();
+    server.send(&json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}));
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+    assert!(stderr.contains("Client closed connection"), "{}", stderr);
+    assert!(!stderr.contains("Error writing"), "{}", stderr);
+}
+
+#[test]
+fn client_disconnect_before_a_parse_error_reply_is_a_normal_shutdown() {
+    let mut server = Server::start(&[]);
+    server.initialize();
+    server.hang_up();
+    server.send_raw(b"not json\n");
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+    assert!(stderr.contains("Client closed connection"), "{}", stderr);
+}
+
+#[test]
+fn slow_queries_are_logged_with_their_arguments() {
+    // Every query takes at least 0ms
+    let mut server = Server::start(&["--slow-query-ms", "0"]);
+    server.initialize();
+    server.send(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}));
+    let add = json!({"name": "addEvent", "arguments": {"id": "e:1", "description": "d", "category": "c"}});
+    server.request(1, "tools/call", add);
+    server.request(2, "tools/call", json!({"name": "findEvents", "arguments": {"prefix": "e:"}}));
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+    assert!(stderr.contains("Warning: slow findEvents (\"e:\") took"), "{}", stderr);
+}
+
+#[cfg(unix)]
+#[test]
+fn sigterm_drains_the_in_flight_request() {
+    let dir = std::env::temp_dir().join(format!("blazing-art-mcp-drain-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("events.json");
+    let events: Vec<Value> = (0..200_000)
+        .map(|i| json!({"id": format!("e:{:06}", i), "timestamp": "2024-01-01T00:00:00Z", "description": "d", "category": "c"}))
+        .collect();
+    std::fs::write(&path, serde_json::to_string(&events).unwrap()).unwrap();
+
+    let mut server = Server::start(&["--events", path.to_str().unwrap(), "--event-limit", "200000"]);
+    server.initialize();
+    server.send(&json!({"jsonrpc": "2.0", "method": "notifications/initialized"}));
+    server.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": {"name": "findEvents", "arguments": {"prefix": "e:"}, "_meta": {"progressToken": "scan"}}
+    }));
+    // The first progress notification means the scan is under way
+    assert_eq!(server.receive()["method"], "notifications/progress");
+    let killed = Command::new("kill").args(["-TERM", &server.child.id().to_string()]).status().unwrap();
+    assert!(killed.success());
+
+    let response = loop {
+        let message = server.receive();
+        if message.get("id").is_some() {
+            break message;
+        }
+    };
+    assert_eq!(response["id"], 1);
+    let text = response["result"]["content"][0]["text"].as_str().unwrap();
+    assert_eq!(serde_json::from_str::<Vec<Value>>(text).unwrap().len(), events.len());
+
+    let (success, stderr) = server.finish();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(success, "{}", stderr);
+    assert!(stderr.contains("with 1 request in flight, draining"), "{}", &stderr[stderr.len().saturating_sub(2000)..]);
+    assert!(stderr.contains("Drain complete"), "{}", &stderr[stderr.len().saturating_sub(2000)..]);
+}
+
+#[test]
+fn content_length_requests_get_content_length_responses() {
+    let mut server = Server::start(&[]);
+    let framed = |message: Value| {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    };
+    let initialize = framed(json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "initialize",
+        "params": {"protocolVersion": "2024-11-05", "capabilities": {}}
+    }));
+    // Split mid-header and mid-body, as a slow transport might
+    let (head, rest) = initialize.split_at(9);
+    let (body, tail) = rest.split_at(rest.len() / 2);
+    for part in [head, body, tail] {
+        server.send_raw(part.as_bytes());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert_eq!(server.receive_framed()["id"], 0);
+
+    // Framing is per message, so a newline client on the same stream still works
+    assert_eq!(server.request(1, "ping", json!({}))["id"], 1);
+    server.send_raw(framed(json!({"jsonrpc": "2.0", "id": 2, "method": "ping"})).as_bytes());
+    assert_eq!(server.receive_framed()["id"], 2);
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+}
+
+#[test]
+fn idle_sessions_close_while_active_ones_survive() {
+    let mut idle = Server::start(&["--idle-timeout", "1"]);
+    let mut active = Server::start(&["--idle-timeout", "1"]);
+    idle.initialize();
+    active.initialize();
+    // Each request resets the timer, so pinging more often than the timeout keeps the session
+    for id in 1..=6 {
+        std::thread::sleep(std::time::Duration::from_millis(400));
+        assert_eq!(active.request(id, "ping", json!({}))["id"], id);
+    }
+    assert!(active.child.try_wait().unwrap().is_none(), "active session was closed");
+
+    // Over twice the timeout has passed without a request
+    assert!(idle.child.try_wait().unwrap().is_some(), "idle session is still open");
+    let (success, stderr) = idle.finish();
+    assert!(success, "{}", stderr);
+    assert!(stderr.contains("closing idle session"), "{}", stderr);
+    let (success, stderr) = active.finish();
+    assert!(success, "{}", stderr);
+    assert!(!stderr.contains("closing idle session"), "{}", stderr);
+}
+
+#[test]
+fn lifecycle_requires_initialize_once() {
+    let mut server = Server::start(&[]);
+    let early = server.request(1, "tools/call", json!({"name": "findEvents", "arguments": {"prefix": "e:"}}));
+    assert_eq!(early["error"]["code"], -32002);
+    assert!(server.initialize()["result"]["serverInfo"].is_object());
+    let again = server.initialize();
+    assert_eq!(again["error"]["code"], -32600);
+    assert!(again["error"]["message"].as_str().unwrap().contains("already initialized"));
+    // The first session is still intact
+    let call = server.request(2, "tools/call", json!({"name": "findEvents", "arguments": {"prefix": "e:"}}));
+    assert!(call["error"].is_null(), "{}", call);
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+
+    let mut server = Server::start(&["--reinitialize", "ack"]);
+    assert!(server.initialize()["result"]["serverInfo"].is_object());
+    assert!(server.initialize()["result"]["serverInfo"].is_object());
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+}
+
+#[test]
+fn require_data_refuses_to_start_on_an_empty_store() {
+    let dir = std::env::temp_dir().join(format!("blazing-art-mcp-require-data-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("events.json");
+    std::fs::write(&path, "[]").unwrap();
+
+    let server = Server::start(&["--events", path.to_str().unwrap(), "--require-data"]);
+    let (success, stderr) = server.finish();
+    assert!(!success, "{}", stderr);
+    assert!(stderr.contains("--require-data: no entities or events loaded"), "{}", stderr);
+    assert!(stderr.contains(&format!("--events: {}", path.display())), "{}", stderr);
+    assert!(stderr.contains("--entities: not given"), "{}", stderr);
+
+    // The same empty store is fine without the flag
+    let mut server = Server::start(&["--events", path.to_str().unwrap()]);
+    assert!(server.initialize()["result"]["serverInfo"].is_object());
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn replica_picks_up_a_rewritten_snapshot() {
+    let dir = std::env::temp_dir().join(format!("blazing-art-mcp-replica-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // Write then rename, as a primary would, so a poll never sees half a file
+    let publish = |summary: &str| {
+        let staging = dir.join("entities.json.tmp");
+        let entities = json!([{"name": "Ada", "summary": summary, "tags": []}]);
+        std::fs::write(&staging, entities.to_string()).unwrap();
+        std::fs::rename(&staging, dir.join("entities.json")).unwrap();
+    };
+    publish("first");
+
+    let mut server = Server::start(&["--replica-of", dir.to_str().unwrap(), "--replica-interval", "1"]);
+    server.initialize();
+    let lookup = |server: &mut Server, id| {
+        let response = server.request(id, "tools/call", json!({"name": "lookupEntity", "arguments": {"name": "Ada"}}));
+        let text = response["result"]["content"][0]["text"].as_str().unwrap().to_string();
+        serde_json::from_str::<Value>(&text).unwrap()["summary"].as_str().unwrap().to_string()
+    };
+    assert_eq!(lookup(&mut server, 1), "first");
+
+    let add = json!({"name": "addEntity", "arguments": {"name": "Alan", "summary": "s"}});
+    let refused = server.request(2, "tools/call", add);
+    assert!(refused["result"]["content"][0]["text"].as_str().unwrap().contains("read-only replica"));
+
+    publish("second");
+    let mut id = 3;
+    while lookup(&mut server, id) != "second" {
+        assert!(id < 50, "replica never reloaded");
+        id += 1;
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+    assert!(stderr.contains("Replica staleness: snapshot written"), "{}", stderr);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn null_delimited_frames_round_trip() {
+    let mut server = Server::start(&["--frame-delimiter", "null"]);
+    // Two requests in one write; the first spans lines, which only NUL framing allows
+    let initialize = "{\n  \"jsonrpc\": \"2.0\",\n  \"id\": 0,\n  \"method\": \"initialize\",\n  \"params\": {\"protocolVersion\": \"2024-11-05\", \"capabilities\": {}}\n}";
+    let list = json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"});
+    server.send_raw(format!("{}\0{}\0", initialize, list).as_bytes());
+
+    let stdout = server.stdout.as_mut().unwrap();
+    let mut frames = Vec::new();
+    for _ in 0..2 {
+        let mut frame = Vec::new();
+        stdout.read_until(0, &mut frame).unwrap();
+        assert_eq!(frame.pop(), Some(0), "frame not NUL-terminated: {:?}", String::from_utf8_lossy(&frame));
+        assert!(!frame.contains(&b'\n'));
+        frames.push(serde_json::from_slice::<Value>(&frame).unwrap());
+    }
+    assert_eq!(frames[0]["id"], 0);
+    assert!(frames[0]["result"]["serverInfo"].is_object());
+    assert_eq!(frames[1]["id"], 1);
+    assert!(frames[1]["result"]["tools"].is_array());
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+}
+
+#[test]
+fn coalesced_bursts_still_answer_every_request() {
+    let mut server = Server::start(&["--coalesce-flushes"]);
+    server.initialize();
+    let burst: String = (1..=20).map(|id| format!("{}\n", json!({"jsonrpc": "2.0", "id": id, "method": "ping"}))).collect();
+    server.send_raw(burst.as_bytes());
+    for id in 1..=20 {
+        assert_eq!(server.receive()["id"], id);
+    }
+    // A lone request after the burst is not held back waiting for more
+    assert_eq!(server.request(21, "ping", json!({}))["id"], 21);
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+}
+
+#[test]
+fn captured_requests_are_the_most_recent_in_order() {
+    let mut server = Server::start(&["--capture-requests", "3"]);
+    server.initialize();
+    for id in 1..=4 {
+        let lookup = json!({"name": "lookupEntity", "arguments": {"name": format!("n{}", id)}});
+        server.request(id, "tools/call", lookup);
+    }
+    server.send_raw(b"{not json\n");
+    server.receive();
+    let dump = server.request(5, "tools/call", json!({"name": "capturedRequests", "arguments": {"format": "jsonl"}}));
+    let lines: Vec<&str> = dump["result"]["content"][0]["text"].as_str().unwrap().lines().collect();
+    // The dump request itself is captured before it runs
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert_eq!(serde_json::from_str::<Value>(lines[0]).unwrap()["id"], 4);
+    assert_eq!(lines[1], "{not json");
+    assert_eq!(serde_json::from_str::<Value>(lines[2]).unwrap()["id"], 5);
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+
+    let mut server = Server::start(&["--capture-requests", "1", "--capture-redact-arguments"]);
+    server.initialize();
+    let captured = server.request(1, "tools/call", json!({"name": "capturedRequests", "arguments": {"secret": "s3cret"}}));
+    let text = captured["result"]["content"][0]["text"].as_str().unwrap();
+    let entries: Value = serde_json::from_str(text).unwrap();
+    let request: Value = serde_json::from_str(entries[0]["request"].as_str().unwrap()).unwrap();
+    assert_eq!(request["params"]["arguments"], "[REDACTED]");
+    assert!(!text.contains("s3cret"));
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+}
+
+#[test]
+fn strict_warmup_failures_stop_startup() {
+    let dir = std::env::temp_dir().join(format!("blazing-art-mcp-warmup-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("warmup.json");
+    std::fs::write(&path, json!([{"tool": "lookupEntity", "arguments": {"name": "Nobody"}}]).to_string()).unwrap();
+
+    let server = Server::start(&["--warmup-file", path.to_str().unwrap(), "--strict-warmup"]);
+    let (success, stderr) = server.finish();
+    assert!(!success, "{}", stderr);
+    assert!(stderr.contains("--strict-warmup: 1 warmup calls failed"), "{}", stderr);
+
+    // Without --strict-warmup the failure is only logged
+    let mut server = Server::start(&["--warmup-file", path.to_str().unwrap()]);
+    assert!(server.initialize()["result"]["serverInfo"].is_object());
+    let (success, stderr) = server.finish();
+    assert!(success, "{}", stderr);
+    assert!(stderr.contains("Warmup: 1 calls in"), "{}", stderr);
+    std::fs::remove_dir_all(&dir).unwrap();
+}